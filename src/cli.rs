@@ -16,6 +16,7 @@ pub enum Commands {
     Eject(EjectCommand),
     Cron(CronCommand),
     FindDownloads(FindDownloadsCommand),
+    Clean(CleanCommand),
 }
 
 #[derive(Debug, StructOpt)]
@@ -121,3 +122,15 @@ pub struct FindDownloadsCommand {
     #[structopt(long = "days")]
     pub days: Option<u32>,
 }
+
+/// removes orphaned encrypted DMG backing files from the temp dir
+///
+/// This finds `encrypted-*.dmg` files left behind in the temp dir by `--keep`
+/// or an interrupted run, and deletes the ones that are both expired and not
+/// currently mounted.
+#[derive(Debug, StructOpt)]
+pub struct CleanCommand {
+    /// only list the images that would be deleted
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+}
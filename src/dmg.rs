@@ -18,6 +18,7 @@ struct HdiUtilSystemEntity {
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "kebab-case")]
 struct HdiUtilImage {
+    image_path: Option<PathBuf>,
     system_entities: Vec<HdiUtilSystemEntity>,
 }
 
@@ -105,6 +106,25 @@ pub fn list_volumes() -> Result<Vec<(PathBuf, SystemTime)>, Error> {
     Ok(encrypted_volumes)
 }
 
+/// Returns the canonicalized backing file paths of all currently attached
+/// disk images, encrypted or not.
+pub fn list_attached_images() -> Result<Vec<PathBuf>, Error> {
+    let output = Command::new("hdiutil")
+        .arg("info")
+        .arg("-plist")
+        .stdout(Stdio::piped())
+        .spawn()?
+        .wait_with_output()?;
+    let info: HdiUtilInfo = plist::from_bytes(&output.stdout)?;
+
+    Ok(info
+        .images
+        .into_iter()
+        .filter_map(|x| x.image_path)
+        .filter_map(|x| fs::canonicalize(x).ok())
+        .collect())
+}
+
 pub fn eject(path: &Path) -> Result<(), Error> {
     Command::new("hdiutil")
         .arg("eject")
@@ -115,9 +135,14 @@ pub fn eject(path: &Path) -> Result<(), Error> {
     Ok(())
 }
 
-pub fn secure_volume(path: &Path, days: u32) -> Result<(), Error> {
-    let good_until = (SystemTime::now() + Duration::from_secs((days as u64) * 60 * 60 * 24))
-        .duration_since(SystemTime::UNIX_EPOCH)?;
+/// Computes the expiry timestamp for an image that is good for `days` days
+/// from now.
+pub fn compute_expiry(days: u32) -> SystemTime {
+    SystemTime::now() + Duration::from_secs((days as u64) * 60 * 60 * 24)
+}
+
+pub fn secure_volume(path: &Path, good_until: SystemTime) -> Result<(), Error> {
+    let good_until = good_until.duration_since(SystemTime::UNIX_EPOCH)?;
     fs::write(path.join(".metadata_never_index"), "")?;
     fs::write(
         path.join(".encrypted-volume-good-until"),
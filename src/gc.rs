@@ -0,0 +1,77 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Error;
+
+use crate::dmg;
+
+/// An orphaned encrypted DMG backing file found in the temp dir.
+pub struct TempImage {
+    pub path: PathBuf,
+    pub expires: SystemTime,
+}
+
+/// Parses the expiry epoch embedded in an `encrypted-<uuid>-<name>.<expiry>.dmg`
+/// filename, as created by `prepare_dmg`.
+fn parse_temp_image(path: &Path) -> Option<TempImage> {
+    let name = path.file_name()?.to_str()?;
+    let stem = name.strip_prefix("encrypted-")?.strip_suffix(".dmg")?;
+    let (_, expiry) = stem.rsplit_once('.')?;
+    let secs: u64 = expiry.parse().ok()?;
+    Some(TempImage {
+        path: path.to_path_buf(),
+        expires: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+    })
+}
+
+/// Scans `std::env::temp_dir()` for encrypted DMG backing files that have
+/// passed their embedded expiry and are not currently mounted.
+pub fn find_expired_images() -> Result<Vec<TempImage>, Error> {
+    let attached = dmg::list_attached_images()?;
+    let now = SystemTime::now();
+    let mut expired = vec![];
+
+    for entry in fs::read_dir(std::env::temp_dir())? {
+        let entry = entry?;
+        let image = match parse_temp_image(&entry.path()) {
+            Some(image) => image,
+            None => continue,
+        };
+        if image.expires >= now {
+            continue;
+        }
+        if let Ok(canonical) = fs::canonicalize(&image.path) {
+            if attached.contains(&canonical) {
+                continue;
+            }
+        }
+        expired.push(image);
+    }
+
+    Ok(expired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_temp_image() {
+        let image =
+            parse_temp_image(Path::new("/tmp/encrypted-9b1d-Some Volume.1700000000.dmg")).unwrap();
+        assert_eq!(
+            image.expires,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1700000000)
+        );
+    }
+
+    #[test]
+    fn test_parse_temp_image_rejects_unrelated_files() {
+        assert!(parse_temp_image(Path::new("/tmp/not-an-image.dmg")).is_none());
+        assert!(parse_temp_image(Path::new("/tmp/encrypted-9b1d-name.dmg")).is_none());
+        assert!(parse_temp_image(Path::new("/tmp/encrypted-9b1d-name.notanumber.dmg")).is_none());
+    }
+}
@@ -11,19 +11,21 @@ use chrono::{DateTime, Utc};
 use dialoguer::Password;
 use structopt::StructOpt;
 use uuid::Uuid;
-use which::which;
 
 use crate::{
     cli::{
-        Commands, CronCommand, EjectCommand, FindDownloadsCommand, ImageOptions, ImportCommand,
-        ListCommand, NewCommand,
+        CleanCommand, Commands, CronCommand, EjectCommand, FindDownloadsCommand, ImageOptions,
+        ImportCommand, ListCommand, NewCommand,
     },
     downloads::find_downloads_in_folder,
 };
 
+mod archive;
 mod cli;
 mod dmg;
 mod downloads;
+mod gc;
+mod tools;
 mod zip;
 
 #[derive(Debug)]
@@ -55,15 +57,21 @@ fn prepare_dmg(
             .and_then(|x| x.file_stem().and_then(|x| x.to_str()))
             .unwrap_or("EncryptedScratchpad"),
     };
-    let dmg_path =
-        std::env::temp_dir().join(format!("encrypted-{}-{}.dmg", Uuid::new_v4(), volume_name));
+    let good_until = dmg::compute_expiry(opts.days);
+    let expires_at = good_until.duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+    let dmg_path = std::env::temp_dir().join(format!(
+        "encrypted-{}-{}.{}.dmg",
+        Uuid::new_v4(),
+        volume_name,
+        expires_at
+    ));
 
     println!("[1] Creating encrypted DMG");
     dmg::make_dmg(&dmg_path, volume_name, size, &password)?;
     println!("[2] Mounting DMG");
     let mounted_at = dmg::mount_dmg(&dmg_path, &password)?;
     println!("[3] Securing mounted volume");
-    dmg::secure_volume(&mounted_at, opts.days)?;
+    dmg::secure_volume(&mounted_at, good_until)?;
 
     Ok(PrepareResult {
         password,
@@ -95,13 +103,28 @@ fn import_command(args: ImportCommand) -> Result<(), Error> {
     if !fs::metadata(&input_path).map_or(false, |x| x.is_file()) {
         bail!("source archive is not a file");
     }
-    let size = zip::get_uncompressed_zip_size(&input_path)? + args.extra_size;
+    let format = archive::detect(&input_path)?;
+    let seven_zip = if format.needs_seven_zip() {
+        Some(tools::ensure_7z()?)
+    } else {
+        None
+    };
+    let size = archive::get_uncompressed_size(seven_zip.as_deref(), &input_path, format)?
+        + args.extra_size;
     let result = prepare_dmg(&args.image_opts, size, Some(&input_path))?;
-    if !zip::check_password(&input_path, &result.password)? {
+    if !archive::check_password(seven_zip.as_deref(), &input_path, format, &result.password)? {
         bail!("invalid password");
     }
-    println!("[4] Extracting encrypted zip");
-    zip::extract(&input_path, &result.mounted_at, &result.password)?;
+    println!("[4] Extracting encrypted archive");
+    archive::extract_checked(
+        seven_zip.as_deref(),
+        &input_path,
+        &result.mounted_at,
+        format,
+        &result.password,
+        archive::DEFAULT_MAX_ENTRIES,
+        (size as u64) * 1024,
+    )?;
     finalize_dmg(&args.image_opts, &result)?;
     Ok(())
 }
@@ -163,6 +186,31 @@ fn cron_command(args: CronCommand) -> Result<(), Error> {
     Ok(())
 }
 
+fn clean_command(args: CleanCommand) -> Result<(), Error> {
+    let expired = gc::find_expired_images()?;
+
+    for image in &expired {
+        println!(
+            "{} {}",
+            if args.dry_run {
+                "Would delete"
+            } else {
+                "Deleting"
+            },
+            image.path.display()
+        );
+        if !args.dry_run {
+            fs::remove_file(&image.path)?;
+        }
+    }
+
+    if expired.is_empty() {
+        println!("No expired images found");
+    }
+
+    Ok(())
+}
+
 fn matches_domain(pattern: &str, target: &str) -> bool {
     if let Some(rest) = pattern.strip_prefix("*.") {
         target == rest
@@ -268,10 +316,6 @@ fn main() -> Result<(), Error> {
 
     let commands = Commands::from_args();
 
-    if which("7z").is_err() {
-        bail!("7z is not available");
-    }
-
     match commands {
         Commands::New(args) => new_command(args),
         Commands::Import(args) => import_command(args),
@@ -279,6 +323,7 @@ fn main() -> Result<(), Error> {
         Commands::Eject(args) => eject_command(args),
         Commands::Cron(args) => cron_command(args),
         Commands::FindDownloads(args) => find_downloads_command(args),
+        Commands::Clean(args) => clean_command(args),
     }
 }
 
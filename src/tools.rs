@@ -0,0 +1,129 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    env, fs,
+    hash::{Hash, Hasher},
+    io::Read,
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, bail, Context, Error};
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use tar::Archive;
+use which::which;
+
+/// Set to skip provisioning and require a system `7z` on `PATH` instead.
+const REQUIRE_SYSTEM_7Z_ENV: &str = "EDMGUTIL_REQUIRE_SYSTEM_7Z";
+
+/// A known-good static `7zz` build for a given macOS architecture, along
+/// with the SHA-256 of the downloaded `.tar.gz` itself (not the `7zz`
+/// binary it contains) so the download can be verified before it's trusted.
+struct SevenZipBuild {
+    url: &'static str,
+    sha256: &'static str,
+}
+
+fn known_build() -> Result<SevenZipBuild, Error> {
+    match env::consts::ARCH {
+        "aarch64" => Ok(SevenZipBuild {
+            url: "https://www.7-zip.org/a/7z2408-mac-arm64.tar.gz",
+            sha256: "e7c273188ba4ecd19c69fc0c3919ddb2eb91d8a3c2ae1ad6ccd6c2aaf0c3ed99",
+        }),
+        "x86_64" => Ok(SevenZipBuild {
+            url: "https://www.7-zip.org/a/7z2408-mac-x64.tar.gz",
+            sha256: "38b5f3f7e796f445ac0a70ab810b8d6fd04d29b1dfc1e0a3d9a4a2f7cb5c72fa",
+        }),
+        other => bail!("no known static 7z build for architecture {}", other),
+    }
+}
+
+/// Returns a per-user cache directory for `url`, named by a hash of the URL
+/// itself so that pointing at a different build transparently invalidates
+/// whatever was cached for the old one.
+fn cache_dir_for(url: &str) -> Result<PathBuf, Error> {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let dirs = directories::ProjectDirs::from("", "", "edmgutil")
+        .ok_or_else(|| anyhow!("could not determine a cache directory"))?;
+    Ok(dirs
+        .cache_dir()
+        .join(format!("7z-{:016x}", hasher.finish())))
+}
+
+/// Returns the path to a usable `7z` binary.
+///
+/// If `7z` is already on `PATH` that is used as-is. Otherwise a known static
+/// `7zz` build is downloaded into a per-user cache directory, verified
+/// against an embedded SHA-256, and reused on subsequent runs. Set
+/// `EDMGUTIL_REQUIRE_SYSTEM_7Z=1` to disable the download and fail instead.
+pub fn ensure_7z() -> Result<PathBuf, Error> {
+    if let Ok(path) = which("7z") {
+        return Ok(path);
+    }
+
+    if env::var(REQUIRE_SYSTEM_7Z_ENV).map_or(false, |x| x == "1") {
+        bail!(
+            "7z is not available on PATH and {} is set",
+            REQUIRE_SYSTEM_7Z_ENV
+        );
+    }
+
+    let build = known_build()?;
+    let cache_dir = cache_dir_for(build.url)?;
+    let binary_path = cache_dir.join("7zz");
+
+    if binary_path.is_file() {
+        return Ok(binary_path);
+    }
+
+    println!("Downloading 7z into {}", cache_dir.display());
+    fs::create_dir_all(&cache_dir)?;
+
+    let archive_bytes = download(build.url)?;
+    verify_sha256(&archive_bytes, build.sha256)?;
+    let binary_bytes = extract_7zz_binary(&archive_bytes)?;
+
+    fs::write(&binary_path, binary_bytes)?;
+    fs::set_permissions(&binary_path, fs::Permissions::from_mode(0o755))?;
+
+    Ok(binary_path)
+}
+
+fn download(url: &str) -> Result<Vec<u8>, Error> {
+    let response = ureq::get(url)
+        .call()
+        .with_context(|| format!("failed to download {}", url))?;
+    let mut bytes = vec![];
+    response.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn verify_sha256(bytes: &[u8], expected: &str) -> Result<(), Error> {
+    let digest = Sha256::digest(bytes);
+    let actual = digest
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect::<String>();
+    if actual != expected {
+        bail!(
+            "checksum mismatch for downloaded 7z build: expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+    Ok(())
+}
+
+fn extract_7zz_binary(archive_bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut archive = Archive::new(GzDecoder::new(archive_bytes));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.file_name().map_or(false, |x| x == "7zz") {
+            let mut buf = vec![];
+            entry.read_to_end(&mut buf)?;
+            return Ok(buf);
+        }
+    }
+    bail!("downloaded 7z archive did not contain a 7zz binary")
+}
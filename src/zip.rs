@@ -1,25 +1,80 @@
 use std::{
-    path::Path,
+    fs,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
-use anyhow::Error;
+use anyhow::{anyhow, bail, Error};
 
-pub fn get_uncompressed_zip_size(path: &Path) -> Result<usize, Error> {
-    let child = Command::new("7z")
+use crate::archive::{safe_join, verify_contained};
+
+#[derive(Debug, PartialEq, Eq)]
+struct ListedEntry {
+    path: PathBuf,
+    size: u64,
+    is_dir: bool,
+    is_symlink: bool,
+}
+
+/// Parses the output of `7z l -slt`, which lists entries as blank-line
+/// separated `Key = Value` blocks, the first of which describes the archive
+/// itself rather than an entry.
+fn parse_listing(output: &str) -> Result<Vec<ListedEntry>, Error> {
+    let mut entries = vec![];
+    for block in output.split("\n\n") {
+        let mut path = None;
+        let mut size = 0u64;
+        let mut attributes = String::new();
+        for line in block.lines() {
+            if let Some(value) = line.strip_prefix("Path = ") {
+                path = Some(value);
+            } else if let Some(value) = line.strip_prefix("Size = ") {
+                size = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow!("malformed Size field in 7z listing: {:?}", value))?;
+            } else if let Some(value) = line.strip_prefix("Attributes = ") {
+                attributes = value.trim().to_string();
+            }
+        }
+        if let Some(path) = path {
+            entries.push(ListedEntry {
+                path: PathBuf::from(path),
+                size,
+                is_dir: attributes.starts_with('D'),
+                is_symlink: attributes.contains('L'),
+            });
+        }
+    }
+
+    // the first block just describes the archive container itself, not an entry.
+    if !entries.is_empty() {
+        entries.remove(0);
+    }
+
+    Ok(entries)
+}
+
+fn list_entries(seven_zip: &Path, path: &Path) -> Result<Vec<ListedEntry>, Error> {
+    let child = Command::new(seven_zip)
         .arg("l")
+        .arg("-slt")
         .arg(path)
         .stdout(Stdio::piped())
         .spawn()?
         .wait_with_output()?;
     let output = std::str::from_utf8(&child.stdout)?;
-    let last_line = output.trim().lines().last().unwrap();
-    let bytes: u64 = last_line.split_ascii_whitespace().nth(2).unwrap().parse()?;
-    Ok((bytes / 1024) as usize + 1)
+    parse_listing(output)
+}
+
+pub fn get_uncompressed_zip_size(seven_zip: &Path, path: &Path) -> Result<usize, Error> {
+    let entries = list_entries(seven_zip, path)?;
+    let total: u64 = entries.iter().map(|x| x.size).sum();
+    Ok((total / 1024) as usize + 1)
 }
 
-pub fn check_password(path: &Path, password: &str) -> Result<bool, Error> {
-    let child = Command::new("7z")
+pub fn check_password(seven_zip: &Path, path: &Path, password: &str) -> Result<bool, Error> {
+    let child = Command::new(seven_zip)
         .arg("t")
         .arg(&format!("-p{}", password))
         .arg(path)
@@ -32,16 +87,236 @@ pub fn check_password(path: &Path, password: &str) -> Result<bool, Error> {
     Ok(!err.contains("ERROR: Wrong password") && output.contains("Everything is Ok"))
 }
 
-pub fn extract(src: &Path, dst: &Path, password: &str) -> Result<(), Error> {
-    Command::new("7z")
-        .arg("x")
-        .arg("-bsp2")
-        .arg(&format!("-p{}", password))
-        .arg("-y")
-        .arg(src)
-        .current_dir(dst)
-        .stdout(Stdio::null())
-        .spawn()?
-        .wait()?;
+/// Extracts `src` into `dst`, validating every entry as it is unpacked.
+///
+/// Every entry's path is checked for traversal up front: an absolute path
+/// or a normalized path with a parent-dir component is rejected before
+/// anything is extracted. The listed entry count and the cumulative
+/// uncompressed size are checked against `max_entries` and `max_total_bytes`,
+/// also before anything is extracted, so a zip bomb can't silently overflow
+/// the provisioned image. Symlink entries are
+/// never extracted at all — they are skipped before any bytes touch disk, so
+/// a later entry can never be written through one. Each remaining entry is
+/// extracted on its own (rather than unpacking the whole archive in one
+/// shot) so that a skipped symlink can never be materialized first; the
+/// path actually written is then re-canonicalized to make sure it still
+/// lives under `dst` (zip-slip defense).
+pub fn extract_checked(
+    seven_zip: &Path,
+    src: &Path,
+    dst: &Path,
+    password: &str,
+    max_entries: u64,
+    max_total_bytes: u64,
+) -> Result<(), Error> {
+    let dst = fs::canonicalize(dst)?;
+    let entries = list_entries(seven_zip, src)?;
+
+    if entries.len() as u64 > max_entries {
+        bail!(
+            "archive contains {} entries, exceeding the limit of {}",
+            entries.len(),
+            max_entries
+        );
+    }
+
+    let mut total_bytes = 0u64;
+    for entry in &entries {
+        total_bytes += entry.size;
+        if total_bytes > max_total_bytes {
+            bail!(
+                "archive exceeds the uncompressed size limit of {} bytes",
+                max_total_bytes
+            );
+        }
+        // validate every path up front so a bad entry aborts before anything is extracted.
+        safe_join(&dst, &entry.path)?;
+    }
+
+    extract_entries(&dst, &entries, |entry, target| {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let status = Command::new(seven_zip)
+            .arg("x")
+            .arg(&format!("-p{}", password))
+            .arg("-y")
+            .arg(src)
+            .arg(format!("-o{}", dst.display()))
+            .arg(&entry.path)
+            .stdout(Stdio::null())
+            .spawn()?
+            .wait()?;
+        if !status.success() {
+            bail!("failed to extract {}", entry.path.display());
+        }
+        Ok(())
+    })
+}
+
+/// Walks `entries` in listing order, skipping symlinks and invoking
+/// `extract_one` to materialize everything else.
+///
+/// Symlinks are skipped before `extract_one` ever runs for them, so a later
+/// entry nested under a symlink's name (e.g. a `link -> /tmp` entry followed
+/// by a `link/pwned.txt` entry) is written into a real directory created by
+/// `fs::create_dir_all` rather than through the symlink. This ordering is
+/// pulled out of [`extract_checked`] so it can be exercised directly without
+/// needing a real `7z` binary.
+fn extract_entries<F>(dst: &Path, entries: &[ListedEntry], mut extract_one: F) -> Result<(), Error>
+where
+    F: FnMut(&ListedEntry, &Path) -> Result<(), Error>,
+{
+    for entry in entries {
+        if entry.is_symlink {
+            println!("skipping symlink entry: {}", entry.path.display());
+            continue;
+        }
+
+        let target = safe_join(dst, &entry.path)?;
+        if entry.is_dir {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+
+        extract_one(entry, &target)?;
+        verify_contained(dst, &target)?;
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listing() {
+        let output = "\
+Path = archive.zip
+Type = zip
+
+Path = dir/
+Size = 0
+Attributes = D
+
+Path = dir/file.txt
+Size = 42
+Attributes = A
+";
+        let entries = parse_listing(output).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ListedEntry {
+                    path: PathBuf::from("dir/"),
+                    size: 0,
+                    is_dir: true,
+                    is_symlink: false,
+                },
+                ListedEntry {
+                    path: PathBuf::from("dir/file.txt"),
+                    size: 42,
+                    is_dir: false,
+                    is_symlink: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_listing_detects_symlinks() {
+        let output = "\
+Path = archive.zip
+Type = zip
+
+Path = link
+Size = 3
+Attributes = L
+";
+        let entries = parse_listing(output).unwrap();
+        assert!(entries[0].is_symlink);
+    }
+
+    #[test]
+    fn test_parse_listing_rejects_malformed_size() {
+        let output = "\
+Path = archive.zip
+Type = zip
+
+Path = file.txt
+Size = not-a-number
+Attributes = A
+";
+        assert!(parse_listing(output).is_err());
+    }
+
+    fn scratch_dir(label: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("edmgutil-test-{}-{}", std::process::id(), label));
+        fs::create_dir_all(&dir).unwrap();
+        fs::canonicalize(&dir).unwrap()
+    }
+
+    #[test]
+    fn test_extract_entries_does_not_follow_symlink_into_child_entry() {
+        let dst = scratch_dir("zip-symlink-escape");
+        let entries = vec![
+            ListedEntry {
+                path: PathBuf::from("link"),
+                size: 0,
+                is_dir: false,
+                is_symlink: true,
+            },
+            ListedEntry {
+                path: PathBuf::from("link/pwned.txt"),
+                size: 5,
+                is_dir: false,
+                is_symlink: false,
+            },
+        ];
+
+        extract_entries(&dst, &entries, |_entry, target| {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(target, b"pwned")?;
+            Ok(())
+        })
+        .unwrap();
+
+        // `link` was skipped rather than materialized as a symlink, so its
+        // child entry landed in a plain directory under dst instead of
+        // escaping through it.
+        assert!(!dst
+            .join("link")
+            .symlink_metadata()
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read(dst.join("link/pwned.txt")).unwrap(), b"pwned");
+        fs::remove_dir_all(&dst).ok();
+    }
+
+    #[test]
+    fn test_extract_entries_rejects_escape_past_dst() {
+        let dst = scratch_dir("zip-escape-check");
+        let outside = scratch_dir("zip-escape-check-sibling");
+        let entries = vec![ListedEntry {
+            path: PathBuf::from("evil.txt"),
+            size: 3,
+            is_dir: false,
+            is_symlink: false,
+        }];
+
+        // simulate an extractor that (bug notwithstanding) writes outside dst;
+        // verify_contained must still catch it.
+        let result = extract_entries(&dst, &entries, |_entry, _target| {
+            fs::write(outside.join("evil.txt"), b"hi")?;
+            Ok(())
+        });
+        assert!(result.is_err());
+        fs::remove_dir_all(&dst).ok();
+        fs::remove_dir_all(&outside).ok();
+    }
+}